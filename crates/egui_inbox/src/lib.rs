@@ -8,6 +8,16 @@ use std::sync::{mpsc, Arc};
 use egui::mutex::Mutex;
 use egui::{Context, Ui};
 
+/// A higher-level task primitive built on [UiInbox] that carries lifecycle status (progress,
+/// success, failure) instead of raw payloads.
+pub mod task;
+
+/// Spawn a long-lived `tokio`/`smol` task that forwards a [`futures_util::Stream`] or
+/// [`std::future::Future`] into a [UiInbox], cancelling it automatically when dropped. Requires
+/// the `tokio` or `smol` feature.
+#[cfg(any(feature = "tokio", feature = "smol"))]
+pub mod spawn;
+
 /// Utility to send messages to egui views from async functions, callbacks, etc. without
 /// having to use interior mutability.
 /// Example:
@@ -45,6 +55,13 @@ pub struct UiInbox<T> {
     state: Arc<Mutex<State>>,
     rx: mpsc::Receiver<T>,
     tx: mpsc::Sender<T>,
+
+    // Tasks spawned via `spawn::UiInbox::spawn_future`/`spawn_stream`, owned directly by this
+    // inbox (not via `state`, which senders also hold a clone of) so that dropping the inbox is
+    // what cancels them, rather than them being kept alive forever by their own sender's clone
+    // of `state`.
+    #[cfg(any(feature = "tokio", feature = "smol"))]
+    spawned: Mutex<Vec<spawn::SpawnGuard>>,
 }
 impl<T> Debug for UiInbox<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -105,7 +122,13 @@ impl<T> UiInbox<T> {
         let (tx, rx) = mpsc::channel();
 
         let state = Arc::new(Mutex::new(State { ctx }));
-        Self { state, rx, tx }
+        Self {
+            state,
+            rx,
+            tx,
+            #[cfg(any(feature = "tokio", feature = "smol"))]
+            spawned: Mutex::new(Vec::new()),
+        }
     }
 
     /// Create a inbox and a sender for it.
@@ -194,6 +217,56 @@ impl<T> UiInbox<T> {
     }
 }
 
+impl<T: Clone> UiInbox<T> {
+    /// Create a broadcast sender and an initial receiver for it.
+    ///
+    /// Unlike [UiInbox::channel], the returned [UiInboxBroadcastSender] can mint further
+    /// independently-draining receivers via [UiInboxBroadcastSender::subscribe], so multiple
+    /// views can observe the same stream of events (e.g. a global "settings changed" signal)
+    /// without stealing items from each other.
+    pub fn broadcast() -> (UiInboxBroadcastSender<T>, Self) {
+        let sender = UiInboxBroadcastSender {
+            senders: Arc::new(Mutex::new(Vec::new())),
+        };
+        let inbox = sender.subscribe();
+        (sender, inbox)
+    }
+}
+
+/// Sender for a broadcast [UiInbox], created via [UiInbox::broadcast]. Fans every sent item out
+/// to every receiver minted with [UiInboxBroadcastSender::subscribe], each of which drains
+/// independently through the regular [UiInbox] API.
+pub struct UiInboxBroadcastSender<T: Clone> {
+    senders: Arc<Mutex<Vec<UiInboxSender<T>>>>,
+}
+
+impl<T: Clone> Clone for UiInboxBroadcastSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            senders: self.senders.clone(),
+        }
+    }
+}
+
+impl<T: Clone> UiInboxBroadcastSender<T> {
+    /// Mint a new receiver that will see every item sent from now on, with its own read cursor
+    /// independent of any other receiver.
+    pub fn subscribe(&self) -> UiInbox<T> {
+        let inbox = UiInbox::new();
+        self.senders.lock().push(inbox.sender());
+        inbox
+    }
+
+    /// Send an item to every current receiver, requesting a repaint on each one's [Context],
+    /// same as [UiInboxSender::send]. Receivers that were dropped are cleaned up as a side
+    /// effect.
+    pub fn send(&self, item: T) {
+        self.senders
+            .lock()
+            .retain(|sender| sender.send(item.clone()).is_ok());
+    }
+}
+
 impl<T> UiInboxSender<T> {
     /// Send an item to the inbox.
     /// Calling this will request a repaint from egui.
@@ -0,0 +1,75 @@
+use std::future::Future;
+
+use futures_util::{pin_mut, Stream, StreamExt as _};
+
+use crate::UiInbox;
+
+/// Handle to a task spawned via [UiInbox::spawn_stream]/[UiInbox::spawn_future], stored inside
+/// the [UiInbox] it was spawned from (see `UiInbox::spawned`) so that dropping the inbox cancels
+/// the task, instead of leaving it to run forever or requiring the caller to keep a guard alive
+/// themselves.
+pub(crate) struct SpawnGuard {
+    #[cfg(feature = "tokio")]
+    handle: tokio::task::JoinHandle<()>,
+    #[cfg(all(feature = "smol", not(feature = "tokio")))]
+    handle: smol::Task<()>,
+}
+
+impl Drop for SpawnGuard {
+    #[cfg(feature = "tokio")]
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+
+    // `smol::Task` already cancels the task on drop, so there's nothing to do here; this impl
+    // just makes that guarantee explicit for `SpawnGuard` itself.
+    #[cfg(all(feature = "smol", not(feature = "tokio")))]
+    fn drop(&mut self) {}
+}
+
+impl<T: Send + 'static> UiInbox<T> {
+    /// Spawn a future that resolves to a single item, forwarding it into this inbox and
+    /// requesting a repaint once it completes.
+    ///
+    /// The task is long-running and cancel-on-drop: it's stored inside this [UiInbox], so
+    /// dropping the inbox stops it, instead of requiring the caller to hold on to a separate
+    /// handle (which would be trivial to drop by accident, e.g. at the end of a button-click
+    /// handler, cancelling the task before it ever does any work).
+    pub fn spawn_future(&self, fut: impl Future<Output = T> + Send + 'static) {
+        let sender = self.sender();
+        let task = async move {
+            sender.send(fut.await).ok();
+        };
+
+        #[cfg(feature = "tokio")]
+        let handle = tokio::task::spawn(task);
+        #[cfg(all(feature = "smol", not(feature = "tokio")))]
+        let handle = smol::spawn(task);
+
+        self.spawned.lock().push(SpawnGuard { handle });
+    }
+
+    /// Spawn a stream, forwarding every item into this inbox and requesting a repaint each time.
+    ///
+    /// Like [UiInbox::spawn_future], the task is owned by this [UiInbox] and is cancelled as
+    /// soon as the inbox is dropped, rather than running forever or requiring a separately-held
+    /// guard.
+    pub fn spawn_stream(&self, stream: impl Stream<Item = T> + Send + 'static) {
+        let sender = self.sender();
+        let task = async move {
+            pin_mut!(stream);
+            while let Some(item) = stream.next().await {
+                if sender.send(item).is_err() {
+                    break;
+                }
+            }
+        };
+
+        #[cfg(feature = "tokio")]
+        let handle = tokio::task::spawn(task);
+        #[cfg(all(feature = "smol", not(feature = "tokio")))]
+        let handle = smol::spawn(task);
+
+        self.spawned.lock().push(SpawnGuard { handle });
+    }
+}
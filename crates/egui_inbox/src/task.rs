@@ -0,0 +1,139 @@
+use egui::Ui;
+
+use crate::{UiInbox, UiInboxSender};
+
+/// Lifecycle status sent from a background task to the UI through a [UiInboxSender], driving
+/// [UiTask::poll]. A task typically sends a handful of [UiTaskStatus::ProgressReport]s while it
+/// works, followed by either a [UiTaskStatus::Payload] on success or a [UiTaskStatus::Failed] on
+/// error. [UiTaskStatus::Finished] is for tasks that don't produce a value at all.
+pub enum UiTaskStatus<T, E> {
+    /// Nothing changed since the last update. Useful as a default/placeholder status.
+    NoUpdate,
+    /// Progress towards completion, e.g. in the range `0.0..=1.0`.
+    ProgressReport(f32),
+    /// The task finished successfully with this value.
+    Payload(T),
+    /// The task failed.
+    Failed(E),
+    /// The task finished, with no value to report.
+    Finished,
+}
+
+/// What a [UiTask] is doing right now, as returned by [UiTask::poll].
+pub enum UiTaskPoll<'a, T, E> {
+    /// Still running. `progress` is the last value reported via [UiTaskStatus::ProgressReport],
+    /// or `0.0` if none was reported yet.
+    Pending {
+        /// The last reported progress, or `0.0` if none was reported yet.
+        progress: f32,
+    },
+    /// Finished successfully.
+    Ready(&'a T),
+    /// Finished with an error.
+    Failed(&'a E),
+    /// Finished, with no value to report (see [UiTaskStatus::Finished]).
+    Done,
+}
+
+enum UiTaskState<T, E> {
+    Pending { progress: f32 },
+    Ready(T),
+    Failed(E),
+    Done,
+}
+
+/// A handle to a background task that reports progress and a final value/error, layered on top
+/// of [UiInbox]/[UiInboxSender]. This lets apps drive progress bars and spinners from
+/// long-running work without hand-rolling channels and shared `Arc<Mutex<..>>` state.
+///
+/// Example:
+/// ```no_run
+/// use eframe::egui;
+/// use egui_inbox::task::{UiTask, UiTaskPoll, UiTaskStatus};
+///
+/// pub fn main() -> eframe::Result<()> {
+///     let mut task: Option<UiTask<String, String>> = None;
+///
+///     eframe::run_simple_native(
+///         "UiTask Example",
+///         Default::default(),
+///         move |ctx, _frame| {
+///             egui::CentralPanel::default().show(ctx, |ui| {
+///                 if let Some(task) = &mut task {
+///                     match task.poll(ui) {
+///                         UiTaskPoll::Pending { progress } => {
+///                             ui.add(egui::ProgressBar::new(progress));
+///                         }
+///                         UiTaskPoll::Ready(value) => {
+///                             ui.label(format!("Done: {value}"));
+///                         }
+///                         UiTaskPoll::Failed(err) => {
+///                             ui.label(format!("Failed: {err}"));
+///                         }
+///                         UiTaskPoll::Done => {
+///                             ui.label("Done");
+///                         }
+///                     }
+///                 } else if ui.button("Start Task").clicked() {
+///                     let (sender, new_task) = UiTask::new();
+///                     task = Some(new_task);
+///                     std::thread::spawn(move || {
+///                         for i in 0..10 {
+///                             std::thread::sleep(std::time::Duration::from_millis(100));
+///                             sender.send(UiTaskStatus::ProgressReport(i as f32 / 10.0)).ok();
+///                         }
+///                         sender.send(UiTaskStatus::Payload("Hello!".to_string())).ok();
+///                     });
+///                 }
+///             });
+///         },
+///     )
+/// }
+/// ```
+pub struct UiTask<T, E = std::convert::Infallible> {
+    inbox: UiInbox<UiTaskStatus<T, E>>,
+    state: UiTaskState<T, E>,
+}
+
+impl<T, E> UiTask<T, E> {
+    /// Create a task and a sender for it to report progress/completion through.
+    pub fn new() -> (UiInboxSender<UiTaskStatus<T, E>>, Self) {
+        let (sender, inbox) = UiInbox::channel();
+        (
+            sender,
+            Self {
+                inbox,
+                state: UiTaskState::Pending { progress: 0.0 },
+            },
+        )
+    }
+
+    /// Poll for the latest progress or final value/error.
+    ///
+    /// The ui is only passed here so we can grab a reference to [egui::Context], same as
+    /// [UiInbox::read].
+    pub fn poll(&mut self, ui: &mut Ui) -> UiTaskPoll<'_, T, E> {
+        for status in self.inbox.read(ui) {
+            match status {
+                UiTaskStatus::NoUpdate => {}
+                UiTaskStatus::ProgressReport(progress) => {
+                    if let UiTaskState::Pending { .. } = self.state {
+                        self.state = UiTaskState::Pending { progress };
+                    }
+                }
+                UiTaskStatus::Payload(value) => self.state = UiTaskState::Ready(value),
+                UiTaskStatus::Failed(err) => self.state = UiTaskState::Failed(err),
+                UiTaskStatus::Finished => self.state = UiTaskState::Done,
+            }
+        }
+
+        match &self.state {
+            UiTaskState::Pending { progress } => UiTaskPoll::Pending {
+                progress: *progress,
+            },
+            UiTaskState::Ready(value) => UiTaskPoll::Ready(value),
+            UiTaskState::Failed(err) => UiTaskPoll::Failed(err),
+            UiTaskState::Done => UiTaskPoll::Done,
+        }
+    }
+}
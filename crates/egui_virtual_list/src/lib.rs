@@ -1,6 +1,55 @@
-use egui::{Rect, Ui, Vec2};
+use egui::{Align, Layout, Rect, Ui, Vec2};
+use std::collections::{HashMap, VecDeque};
 use std::ops::Range;
 
+mod tree;
+pub use tree::VirtualListTree;
+
+// Upper bound on the number of entries kept in `VirtualList::item_size_cache`, so that
+// enabling it doesn't grow unbounded memory usage for multi-million-item lists.
+const DEFAULT_ITEM_SIZE_CACHE_LIMIT: usize = 10_000;
+
+// Per-item size cache keyed by item index, with FIFO-ish eviction once `limit` entries are
+// stored. Eviction is by insertion order rather than a strict LRU (a cache hit for an
+// already-known item doesn't bump it to the back), but that's enough to stop whichever items
+// happen to be visited first from permanently claiming the whole cache budget in a
+// multi-million-item list, which would otherwise starve every other region of the list of any
+// benefit from `VirtualList::with_cached_heights`.
+#[derive(Debug, Default)]
+struct ItemSizeCache {
+    sizes: HashMap<usize, Vec2>,
+    order: VecDeque<usize>,
+}
+
+impl ItemSizeCache {
+    fn clear(&mut self) {
+        self.sizes.clear();
+        self.order.clear();
+    }
+
+    fn insert(&mut self, item: usize, size: Vec2, limit: usize) {
+        if self.sizes.insert(item, size).is_none() {
+            self.order.push_back(item);
+        }
+        while self.order.len() > limit {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.sizes.remove(&oldest);
+        }
+    }
+
+    // Sum of the known sizes' heights for items within `range`, and how many of them there are.
+    fn known_height_in(&self, range: Range<usize>) -> (f32, usize) {
+        self.sizes
+            .iter()
+            .filter(|(&item, _)| range.contains(&item))
+            .fold((0.0, 0usize), |(height, count), (_, size)| {
+                (height + size.y, count + 1)
+            })
+    }
+}
+
 pub struct VirtualListResponse {
     /// The range of items that was displayed
     pub item_range: Range<usize>,
@@ -9,6 +58,11 @@ pub struct VirtualListResponse {
     pub newly_visible_items: Range<usize>,
     /// Any items in this range are no longer visible
     pub hidden_items: Range<usize>,
+
+    /// Only set in bottom-up mode (see [`VirtualList::new_bottom_up`]): whether the bottom of
+    /// the content is within the viewport, i.e. whether the caller should auto-scroll to the
+    /// bottom when a new item is appended, to stay "stuck" to the latest item.
+    pub stuck_to_bottom: bool,
 }
 
 #[derive(Debug)]
@@ -30,6 +84,20 @@ pub struct VirtualList {
 
     // We will recalculate every item's rect if the scroll area's width changes
     last_width: f32,
+
+    // An item we should scroll to, once we know (or can estimate) its position
+    scroll_target: Option<(usize, Align)>,
+
+    // Per-item size cache, keyed by item index rather than row index (items can span multiple
+    // rows, or multiple items can share one). Like `rows`, it's cleared on a width change, since
+    // a different width can wrap text to a different height. `None` when `with_cached_heights`
+    // wasn't enabled.
+    item_size_cache: Option<ItemSizeCache>,
+    item_size_cache_limit: usize,
+
+    // When set, items are laid out from the last index upward, anchored to the bottom of the
+    // scroll area, for chat-/log-style feeds.
+    reversed: bool,
 }
 
 impl VirtualList {
@@ -41,9 +109,69 @@ impl VirtualList {
             average_row_size: None,
             rows: vec![],
             average_items_per_row: None,
+            scroll_target: None,
+            item_size_cache: None,
+            item_size_cache_limit: DEFAULT_ITEM_SIZE_CACHE_LIMIT,
+            reversed: false,
         }
     }
 
+    /// Create a list that's anchored to the bottom of its scroll area and grows upward, so
+    /// item `length - 1` (the most recently appended one) sits at the bottom. Useful for
+    /// chat-style feeds and logs, combined with [`VirtualListResponse::stuck_to_bottom`] to
+    /// auto-scroll to new items.
+    pub fn new_bottom_up() -> Self {
+        let mut this = Self::new();
+        this.reversed = true;
+        this
+    }
+
+    /// Switch between top-down (default) and bottom-up (see [`VirtualList::new_bottom_up`])
+    /// layout. Changing this resets the list, since cached rows are only valid for one
+    /// direction.
+    pub fn set_reversed(&mut self, reversed: bool) {
+        if self.reversed != reversed {
+            self.reversed = reversed;
+            self.reset();
+        }
+    }
+
+    /// Enable (or disable) a per-item size cache, keyed by item index. When enabled, the
+    /// estimated remaining scroll height is computed from the sum of known item heights plus
+    /// the running average only for the still-unknown tail, instead of extrapolating the whole
+    /// remainder from the average. This gives a much more stable scrollbar thumb in large lists,
+    /// at the cost of a bounded amount of extra memory (see
+    /// [`VirtualList::with_item_size_cache_limit`]). Like the rest of the list's cached layout,
+    /// it's invalidated whenever the available width changes, since item heights can depend on
+    /// wrapped text width.
+    #[must_use]
+    pub fn with_cached_heights(mut self, enabled: bool) -> Self {
+        self.item_size_cache = enabled.then(ItemSizeCache::default);
+        self
+    }
+
+    /// Set the maximum number of entries kept in the per-item size cache enabled by
+    /// [`VirtualList::with_cached_heights`]. Defaults to 10,000 items.
+    #[must_use]
+    pub fn with_item_size_cache_limit(mut self, limit: usize) -> Self {
+        self.item_size_cache_limit = limit;
+        self
+    }
+
+    /// Scroll so that the item at `index` is aligned to `align` within the viewport, as soon
+    /// as it is laid out (on this frame if its row is already measured, otherwise as soon as
+    /// scrolling reaches it).
+    ///
+    /// If the row containing `index` hasn't been measured yet, the target offset is estimated
+    /// from `average_row_size`/`average_items_per_row` and refined every frame as more rows get
+    /// measured, converging once the target row is actually laid out.
+    ///
+    /// Only the top-down layout scrolls to the target; in bottom-up mode (see
+    /// [`VirtualList::new_bottom_up`]) it is kept but never acted on.
+    pub fn scroll_to_item(&mut self, index: usize, align: Align) {
+        self.scroll_target = Some((index, align));
+    }
+
     /// Layout gets called with the index of the first item that should be displayed.
     /// It should return the number of items that were displayed.
     pub fn ui_custom_layout(
@@ -52,12 +180,19 @@ impl VirtualList {
         length: usize,
         mut layout: impl FnMut(&mut Ui, usize) -> usize,
     ) -> VirtualListResponse {
+        if self.reversed {
+            return self.ui_custom_layout_reversed(ui, length, layout);
+        }
+
         let item_range = ui
             .scope(|ui| {
                 if ui.available_width() != self.last_width {
                     self.last_known_row_index = None;
                     self.last_width = ui.available_width();
                     self.rows.clear();
+                    if let Some(cache) = &mut self.item_size_cache {
+                        cache.clear();
+                    }
                 }
 
                 // Start of the scroll area (!=0 after scrolling)
@@ -102,6 +237,12 @@ impl VirtualList {
 
                         let range = current_item_index..current_item_index + count;
 
+                        if let Some(cache) = &mut self.item_size_cache {
+                            for item in range.clone() {
+                                cache.insert(item, rect.size(), self.item_size_cache_limit);
+                            }
+                        }
+
                         if let Some(row) = self.rows.get_mut(current_row) {
                             row.range = range;
                             row.rect = rect;
@@ -146,13 +287,51 @@ impl VirtualList {
                 let item_range = item_start_index..current_item_index;
 
                 if item_range.end < length {
+                    let remaining = length - item_range.end;
+                    // Sum the heights we already know for items below the visible range,
+                    // iterating the (bounded) cache rather than the (potentially huge)
+                    // remaining range, and only fall back to the average for the rest.
+                    let (known_height, known_count) = self
+                        .item_size_cache
+                        .as_ref()
+                        .map(|cache| cache.known_height_in(item_range.end..length))
+                        .unwrap_or((0.0, 0));
+
+                    let unknown_count = remaining.saturating_sub(known_count);
                     ui.set_min_height(
-                        (length - item_range.end) as f32
-                            / self.average_items_per_row.unwrap_or(1.0)
-                            * self.average_row_size.unwrap_or(Vec2::ZERO).y,
+                        known_height
+                            + unknown_count as f32 / self.average_items_per_row.unwrap_or(1.0)
+                                * self.average_row_size.unwrap_or(Vec2::ZERO).y,
                     );
                 }
 
+                if let Some((index, align)) = self.scroll_target {
+                    if index >= length {
+                        // Out of range: there's nothing to converge to, so drop the target
+                        // instead of re-estimating (and re-scrolling towards) it forever.
+                        self.scroll_target = None;
+                    } else if let Some(row) =
+                        self.rows.iter().find(|row| row.range.contains(&index))
+                    {
+                        ui.scroll_to_rect(row.rect.translate(min.to_vec2()), Some(align));
+                        self.scroll_target = None;
+                    } else {
+                        // The target row hasn't been measured yet, so estimate its offset from
+                        // the averages seen so far. This gets refined every frame as more rows
+                        // are measured, converging once the target row is actually laid out.
+                        let estimated_y = index as f32 / self.average_items_per_row.unwrap_or(1.0)
+                            * self.average_row_size.unwrap_or(Vec2::ZERO).y;
+                        let estimated_rect = Rect::from_min_size(
+                            min + Vec2::new(0.0, estimated_y),
+                            Vec2::new(
+                                ui.available_width(),
+                                self.average_row_size.unwrap_or(Vec2::ZERO).y,
+                            ),
+                        );
+                        ui.scroll_to_rect(estimated_rect, Some(align));
+                    }
+                }
+
                 item_range
             })
             .inner;
@@ -174,14 +353,218 @@ impl VirtualList {
             item_range: item_range,
             newly_visible_items: visible_range,
             hidden_items: hidden_range,
+            stuck_to_bottom: false,
+        }
+    }
+
+    // Mirror of `ui_custom_layout` for `reversed` lists: rows are discovered and laid out
+    // starting from the last item and filling upward, anchored to the bottom of the scroll
+    // area, instead of starting from item 0 and filling downward from the top.
+    fn ui_custom_layout_reversed(
+        &mut self,
+        ui: &mut Ui,
+        length: usize,
+        mut layout: impl FnMut(&mut Ui, usize) -> usize,
+    ) -> VirtualListResponse {
+        let (item_range, stuck_to_bottom) = ui
+            .scope(|ui| {
+                if ui.available_width() != self.last_width {
+                    self.last_known_row_index = None;
+                    self.last_width = ui.available_width();
+                    self.rows.clear();
+                    if let Some(cache) = &mut self.item_size_cache {
+                        cache.clear();
+                    }
+                }
+
+                let min = ui.next_widget_position();
+                let visible_rect = ui.clip_rect().translate(-ui.min_rect().min.to_vec2());
+
+                // We were stuck to the bottom if the bottom-most known row (as measured last
+                // frame, before any new item was appended) ended within the viewport (or we
+                // don't have any rows yet, i.e. this is the first frame).
+                let stuck_to_bottom = self
+                    .rows
+                    .first()
+                    .map_or(true, |row| row.rect.max.y <= visible_rect.max.y + 1.0);
+
+                // If the list's length changed since we last measured the bottom row (e.g. a
+                // new item was appended), every cached row's implicit "distance from the bottom"
+                // is now wrong: `rows[0]` is the bottom-most row, so compare it (not `last()`,
+                // which is the top-most row explored so far). We can't just drop `rows[0]` and
+                // keep the rest, since their positions are all anchored relative to the bottom,
+                // so clear everything and let it get re-measured below from the new tail.
+                if let Some(first_row) = self.rows.first() {
+                    if first_row.range.end != length {
+                        self.rows.clear();
+                        self.last_known_row_index = None;
+                    }
+                }
+
+                let mut row_start_index = self.last_known_row_index.unwrap_or(0);
+
+                // Find the row closest to the bottom of the viewport, walking down from the
+                // last-measured (top-most) row towards `rows[0]`.
+                loop {
+                    if row_start_index == 0 {
+                        break;
+                    }
+
+                    if let Some(row) = self.rows.get(row_start_index) {
+                        if row.rect.max.y >= visible_rect.max.y {
+                            ui.add_space(visible_rect.max.y - row.rect.max.y);
+                            break;
+                        }
+                    }
+                    row_start_index -= 1;
+                }
+                let mut current_row = row_start_index;
+
+                let item_start_index = self
+                    .rows
+                    .get(row_start_index)
+                    .map(|row| row.range.end)
+                    .unwrap_or(length);
+
+                let mut current_item_index = item_start_index;
+
+                ui.with_layout(Layout::bottom_up(Align::Min), |ui| loop {
+                    if current_item_index > 0 {
+                        let scoped = ui.scope(|ui| layout(ui, current_item_index - 1));
+                        let count = scoped.inner.max(1);
+                        let rect = scoped.response.rect.translate(-(min.to_vec2()));
+
+                        let range = current_item_index.saturating_sub(count)..current_item_index;
+
+                        if let Some(cache) = &mut self.item_size_cache {
+                            for item in range.clone() {
+                                cache.insert(item, rect.size(), self.item_size_cache_limit);
+                            }
+                        }
+
+                        if let Some(row) = self.rows.get_mut(current_row) {
+                            row.range = range.clone();
+                            row.rect = rect;
+                        } else {
+                            self.rows.push(RowData {
+                                range: range.clone(),
+                                rect,
+                            });
+                            self.average_row_size = Some(
+                                self.average_row_size
+                                    .map(|size| {
+                                        (current_row as f32 * size + rect.size())
+                                            / (current_row as f32 + 1.0)
+                                    })
+                                    .unwrap_or(rect.size()),
+                            );
+
+                            self.average_items_per_row = Some(
+                                self.average_items_per_row
+                                    .map(|avg_count| {
+                                        (current_row as f32 * avg_count + count as f32)
+                                            / (current_row as f32 + 1.0)
+                                    })
+                                    .unwrap_or(count as f32),
+                            );
+
+                            self.last_known_row_index = Some(current_row);
+                        }
+
+                        current_item_index = range.start;
+
+                        if rect.min.y < visible_rect.min.y {
+                            break;
+                        }
+                    } else {
+                        break;
+                    }
+
+                    current_row += 1;
+                });
+
+                let item_range = current_item_index..item_start_index;
+
+                if item_range.start > 0 {
+                    let remaining = item_range.start;
+                    let (known_height, known_count) = self
+                        .item_size_cache
+                        .as_ref()
+                        .map(|cache| cache.known_height_in(0..item_range.start))
+                        .unwrap_or((0.0, 0));
+
+                    let unknown_count = remaining.saturating_sub(known_count);
+                    ui.set_min_height(
+                        known_height
+                            + unknown_count as f32 / self.average_items_per_row.unwrap_or(1.0)
+                                * self.average_row_size.unwrap_or(Vec2::ZERO).y,
+                    );
+                }
+
+                (item_range, stuck_to_bottom)
+            })
+            .inner;
+
+        let mut hidden_range =
+            self.previous_item_range.start..item_range.start.min(self.previous_item_range.end);
+        if hidden_range.len() <= 0 {
+            hidden_range =
+                item_range.end.max(self.previous_item_range.start)..self.previous_item_range.end;
+        }
+
+        let mut visible_range = self.previous_item_range.end.max(item_range.start)..item_range.end;
+        if visible_range.len() <= 0 {
+            visible_range =
+                self.previous_item_range.start..item_range.start.min(self.previous_item_range.end);
+        }
+
+        VirtualListResponse {
+            item_range,
+            newly_visible_items: visible_range,
+            hidden_items: hidden_range,
+            stuck_to_bottom,
         }
     }
 
+    /// Like [`VirtualList::ui_custom_layout`], but for tree-shaped data flattened via a
+    /// [`VirtualListTree`]. `layout` lays out a single node, given its id and depth; only nodes
+    /// that actually end up in the viewport are looked up via
+    /// [`VirtualListTree::nth_visible`], rather than iterating the whole visible tree every
+    /// frame.
+    pub fn ui_custom_layout_tree<Id: Clone + Eq + std::hash::Hash>(
+        &mut self,
+        ui: &mut Ui,
+        tree: &VirtualListTree<Id>,
+        mut layout: impl FnMut(&mut Ui, Id, usize) -> usize,
+    ) -> VirtualListResponse {
+        let length = tree.visible_len();
+
+        self.ui_custom_layout(ui, length, |ui, i| {
+            let (id, depth) = tree
+                .nth_visible(i)
+                .expect("index within `length` is always a valid visible node");
+            layout(ui, id, depth)
+        })
+    }
+
+    /// Discard cached row rects from `row_index` onwards, forcing them to be recalculated next
+    /// frame. Call this after toggling a [`VirtualListTree`] node's collapsed state, passing the
+    /// row index [`VirtualListTree::set_collapsed`] returns, so that rows below it (whose
+    /// position just changed) are laid out again instead of reusing stale rects.
+    pub fn invalidate_from_row(&mut self, row_index: usize) {
+        self.rows.truncate(row_index);
+        self.last_known_row_index = row_index.checked_sub(1);
+    }
+
     pub fn reset(&mut self) {
         self.last_known_row_index = None;
         self.last_width = 0.0;
         self.average_row_size = None;
         self.rows.clear();
         self.average_items_per_row = None;
+        self.scroll_target = None;
+        if let Some(cache) = &mut self.item_size_cache {
+            cache.clear();
+        }
     }
 }
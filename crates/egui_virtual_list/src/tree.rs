@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+#[derive(Debug, Clone)]
+struct TreeNode<Id> {
+    parent: Option<Id>,
+    children: Vec<Id>,
+    collapsed: bool,
+    // Number of currently-visible descendants, including self.
+    len: usize,
+}
+
+/// A tree of nodes that can be flattened into the linear index space
+/// [`VirtualList::ui_custom_layout_tree`] expects, with O(1) lookup of each node's visible
+/// subtree size.
+///
+/// Collapsing a node hides its descendants from the flattened index space without removing
+/// them from the tree, so toggling it back open is just a `len` recompute rather than a
+/// re-traversal of the whole tree.
+#[derive(Debug, Default)]
+pub struct VirtualListTree<Id: Eq + Hash + Clone> {
+    nodes: HashMap<Id, TreeNode<Id>>,
+    roots: Vec<Id>,
+}
+
+impl<Id: Eq + Hash + Clone> VirtualListTree<Id> {
+    /// Create an empty tree.
+    pub fn new() -> Self {
+        Self {
+            nodes: HashMap::new(),
+            roots: Vec::new(),
+        }
+    }
+
+    /// Insert a leaf node as the last child of `parent`, or as a root if `parent` is `None`.
+    pub fn insert(&mut self, id: Id, parent: Option<Id>) {
+        self.nodes.insert(
+            id.clone(),
+            TreeNode {
+                parent: parent.clone(),
+                children: Vec::new(),
+                collapsed: false,
+                len: 1,
+            },
+        );
+
+        match &parent {
+            Some(parent_id) => {
+                if let Some(parent_node) = self.nodes.get_mut(parent_id) {
+                    parent_node.children.push(id.clone());
+                }
+            }
+            None => self.roots.push(id.clone()),
+        }
+
+        self.recompute_ancestors(parent);
+    }
+
+    /// Whether `id`'s children are currently hidden.
+    pub fn is_collapsed(&self, id: &Id) -> bool {
+        self.nodes.get(id).is_some_and(|node| node.collapsed)
+    }
+
+    /// Collapse or expand `id`, recomputing the visible `len` of `id` and all of its ancestors.
+    /// Returns `id`'s row index in the flattened, visible-only order (same indexing as
+    /// [`VirtualListTree::nth_visible`]/[`VirtualListTree::iter`]) so the caller can pass it
+    /// straight to [`VirtualList::invalidate_from_row`]; `None` if `id` is unknown or hidden
+    /// inside a collapsed ancestor.
+    pub fn set_collapsed(&mut self, id: &Id, collapsed: bool) -> Option<usize> {
+        let node = self.nodes.get_mut(id)?;
+        if node.collapsed != collapsed {
+            node.collapsed = collapsed;
+
+            let parent = self.nodes.get(id).and_then(|node| node.parent.clone());
+            self.recompute_len(id.clone());
+            self.recompute_ancestors(parent);
+        }
+
+        self.rank_of(id)
+    }
+
+    /// Total number of currently-visible rows across the whole tree.
+    pub fn visible_len(&self) -> usize {
+        self.roots
+            .iter()
+            .map(|id| self.nodes.get(id).map_or(0, |node| node.len))
+            .sum()
+    }
+
+    /// Look up the visible node at flattened index `rank` (same order as [`VirtualListTree::iter`]),
+    /// returning its id and depth. This descends through the cached `len`s on the path to the
+    /// node, skipping each preceding sibling's subtree in one step, so it visits only the
+    /// siblings along that path rather than walking the whole visible tree —
+    /// used by [`VirtualList::ui_custom_layout_tree`] to look up only the rows actually laid out.
+    pub fn nth_visible(&self, rank: usize) -> Option<(Id, usize)> {
+        let mut rank = rank;
+        let mut depth = 0;
+        let mut siblings: &[Id] = &self.roots;
+
+        loop {
+            let mut next = None;
+            for id in siblings {
+                let node = self.nodes.get(id)?;
+                if rank == 0 {
+                    return Some((id.clone(), depth));
+                }
+                if rank < node.len {
+                    rank -= 1;
+                    next = Some(&node.children);
+                    break;
+                }
+                rank -= node.len;
+            }
+            siblings = next?;
+            depth += 1;
+        }
+    }
+
+    /// Row index of `id` in the flattened, visible-only order, or `None` if `id` is unknown or
+    /// currently hidden inside a collapsed ancestor.
+    pub fn rank_of(&self, id: &Id) -> Option<usize> {
+        if !self.is_visible(id) {
+            return None;
+        }
+
+        let mut rank = 0;
+        let mut current = id.clone();
+        loop {
+            let node = self.nodes.get(&current)?;
+            let siblings = match &node.parent {
+                Some(parent_id) => &self.nodes.get(parent_id)?.children,
+                None => &self.roots,
+            };
+            for sibling in siblings {
+                if *sibling == current {
+                    break;
+                }
+                rank += self.nodes.get(sibling).map_or(0, |node| node.len);
+            }
+
+            match &node.parent {
+                Some(parent_id) => {
+                    // The parent itself precedes all of its children in pre-order.
+                    rank += 1;
+                    current = parent_id.clone();
+                }
+                None => return Some(rank),
+            }
+        }
+    }
+
+    /// Whether `id` (and all of its ancestors) are expanded, i.e. whether it currently appears
+    /// in [`VirtualListTree::iter`]/[`VirtualListTree::nth_visible`].
+    fn is_visible(&self, id: &Id) -> bool {
+        let Some(node) = self.nodes.get(id) else {
+            return false;
+        };
+        match &node.parent {
+            Some(parent_id) => match self.nodes.get(parent_id) {
+                Some(parent) => !parent.collapsed && self.is_visible(parent_id),
+                None => false,
+            },
+            None => true,
+        }
+    }
+
+    /// Iterate the currently-visible nodes in display order as `(id, visible_len, depth)`,
+    /// skipping the descendants of collapsed nodes.
+    pub fn iter(&self) -> impl Iterator<Item = (Id, usize, usize)> + '_ {
+        let mut stack: Vec<_> = self.roots.iter().rev().map(|id| (id.clone(), 0)).collect();
+        std::iter::from_fn(move || {
+            let (id, depth) = stack.pop()?;
+            let node = self.nodes.get(&id)?;
+            if !node.collapsed {
+                for child in node.children.iter().rev() {
+                    stack.push((child.clone(), depth + 1));
+                }
+            }
+            Some((id, node.len, depth))
+        })
+    }
+
+    fn recompute_len(&mut self, id: Id) {
+        let Some(node) = self.nodes.get(&id) else {
+            return;
+        };
+        let len = if node.collapsed {
+            1
+        } else {
+            1 + node
+                .children
+                .iter()
+                .map(|child| self.nodes.get(child).map_or(0, |node| node.len))
+                .sum::<usize>()
+        };
+        if let Some(node) = self.nodes.get_mut(&id) {
+            node.len = len;
+        }
+    }
+
+    fn recompute_ancestors(&mut self, mut parent: Option<Id>) {
+        while let Some(id) = parent {
+            self.recompute_len(id.clone());
+            parent = self.nodes.get(&id).and_then(|node| node.parent.clone());
+        }
+    }
+}